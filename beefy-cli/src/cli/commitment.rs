@@ -0,0 +1,413 @@
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::cli::utils::Bytes;
+use beefy_primitives::{crypto::Signature, known_payload_ids::MMR_ROOT_ID, Commitment, Payload, ValidatorSetId};
+use parity_scale_codec::{Decode, Encode};
+use sp_core::{ecdsa, hashing::keccak_256, Pair, H256};
+use structopt::StructOpt;
+
+/// Signed commitment as produced by BEEFY on Polkadot-compatible chains.
+type SignedCommitment = beefy_primitives::SignedCommitment<u32, Signature>;
+
+/// Build the payload BEEFY validators sign for a given commitment.
+#[derive(StructOpt)]
+#[structopt(about = "Construct a commitment and print its SCALE encoding and signing prehash")]
+pub struct CommitmentPayload {
+	/// Block number the commitment is made for.
+	#[structopt(long)]
+	pub block: u32,
+	/// Id of the validator set that signs the commitment.
+	#[structopt(long)]
+	pub set_id: ValidatorSetId,
+	/// MMR root hash committed to (stored under the `mh` payload id).
+	#[structopt(long)]
+	pub payload: H256,
+}
+
+impl CommitmentPayload {
+	pub fn run(self) -> anyhow::Result<()> {
+		let commitment = mmr_root_commitment(self.block, self.set_id, self.payload);
+		let (encoded, prehash) = signing_payload(&commitment);
+
+		println!();
+		println!("SCALE-encoded commitment: 0x{}", hex::encode(&encoded));
+		println!("Signing prehash (keccak256): 0x{}", hex::encode(prehash));
+		println!();
+
+		Ok(())
+	}
+}
+
+/// Construct a commitment carrying only the MMR root, the same way the BEEFY worker does.
+pub fn mmr_root_commitment(block_number: u32, validator_set_id: ValidatorSetId, root: H256) -> Commitment<u32> {
+	Commitment {
+		payload: Payload::new(MMR_ROOT_ID, root.encode()),
+		block_number,
+		validator_set_id,
+	}
+}
+
+/// Return SCALE-encoded commitment and the keccak256 hash of it.
+///
+/// The hash is exactly what `BeefyKeystore::sign` signs with `sign_prehashed`.
+pub fn signing_payload(commitment: &Commitment<u32>) -> (Vec<u8>, [u8; 32]) {
+	let encoded = commitment.encode();
+	let prehash = keccak_256(&encoded);
+	(encoded, prehash)
+}
+
+/// Sign a commitment with a local ECDSA key.
+#[derive(StructOpt)]
+#[structopt(about = "Sign a SCALE-encoded commitment the same way a BEEFY validator does")]
+pub struct SignCommitment {
+	/// Secret seed or URI of the ECDSA key (e.g. `//Alice`).
+	#[structopt(long)]
+	pub seed: String,
+	/// SCALE-encoded commitment to sign.
+	#[structopt(long)]
+	pub commitment: Bytes,
+}
+
+impl SignCommitment {
+	pub fn run(self) -> anyhow::Result<()> {
+		let pair =
+			ecdsa::Pair::from_string(&self.seed, None).map_err(|e| anyhow::format_err!("Invalid seed: {:?}", e))?;
+		let commitment = Commitment::<u32>::decode(&mut &*self.commitment.0)?;
+		let signature = sign_commitment(&pair, &commitment);
+
+		println!();
+		println!("Public key: 0x{}", hex::encode(pair.public()));
+		println!("Signature: 0x{}", hex::encode(signature));
+		println!();
+
+		Ok(())
+	}
+}
+
+/// Sign the keccak256 hash of SCALE-encoded commitment, mirroring `BeefyKeystore::sign`.
+pub fn sign_commitment(pair: &ecdsa::Pair, commitment: &Commitment<u32>) -> ecdsa::Signature {
+	let (_, prehash) = signing_payload(commitment);
+	pair.sign_prehashed(&prehash)
+}
+
+/// Compare two signed commitments field by field.
+#[derive(StructOpt)]
+#[structopt(about = "Decode two signed commitments and print the fields they differ in")]
+pub struct CommitmentDiff {
+	/// First SCALE-encoded signed commitment.
+	#[structopt(long)]
+	pub a: Bytes,
+	/// Second SCALE-encoded signed commitment.
+	#[structopt(long)]
+	pub b: Bytes,
+}
+
+impl CommitmentDiff {
+	pub fn run(self) -> anyhow::Result<()> {
+		let a = SignedCommitment::decode(&mut &*self.a.0)?;
+		let b = SignedCommitment::decode(&mut &*self.b.0)?;
+
+		let differences = diff_commitments(&a, &b);
+		println!();
+		if differences.is_empty() {
+			println!("Commitments are identical.");
+		}
+		for difference in differences {
+			println!("{}", difference);
+		}
+		println!();
+
+		Ok(())
+	}
+}
+
+/// A single field two signed commitments differ in.
+#[derive(Debug, PartialEq)]
+pub enum Difference {
+	/// Commitments are for different blocks.
+	BlockNumber(u32, u32),
+	/// Commitments are signed by different validator sets.
+	ValidatorSetId(ValidatorSetId, ValidatorSetId),
+	/// Commitments carry different payloads.
+	Payload(Payload, Payload),
+	/// Commitments have a different number of signature slots.
+	SignaturesLen(usize, usize),
+	/// Signature slot at given index differs.
+	Signature(usize, Option<Signature>, Option<Signature>),
+}
+
+impl std::fmt::Display for Difference {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::BlockNumber(a, b) => write!(f, "block_number: {} vs {}", a, b),
+			Self::ValidatorSetId(a, b) => write!(f, "validator_set_id: {} vs {}", a, b),
+			Self::Payload(a, b) => write!(f, "payload: {:?} vs {:?}", a, b),
+			Self::SignaturesLen(a, b) => write!(f, "signatures.len(): {} vs {}", a, b),
+			Self::Signature(index, a, b) => write!(f, "signatures[{}]: {:?} vs {:?}", index, a, b),
+		}
+	}
+}
+
+/// Return all differences between two signed commitments.
+///
+/// Signature slots are compared up to the length of the shorter vector; a length mismatch
+/// is reported separately.
+pub fn diff_commitments(a: &SignedCommitment, b: &SignedCommitment) -> Vec<Difference> {
+	let mut differences = vec![];
+	let (ca, cb) = (&a.commitment, &b.commitment);
+
+	if ca.block_number != cb.block_number {
+		differences.push(Difference::BlockNumber(ca.block_number, cb.block_number));
+	}
+	if ca.validator_set_id != cb.validator_set_id {
+		differences.push(Difference::ValidatorSetId(ca.validator_set_id, cb.validator_set_id));
+	}
+	if ca.payload != cb.payload {
+		differences.push(Difference::Payload(ca.payload.clone(), cb.payload.clone()));
+	}
+	if a.signatures.len() != b.signatures.len() {
+		differences.push(Difference::SignaturesLen(a.signatures.len(), b.signatures.len()));
+	}
+	for (index, (sa, sb)) in a.signatures.iter().zip(&b.signatures).enumerate() {
+		if sa != sb {
+			differences.push(Difference::Signature(index, sa.clone(), sb.clone()));
+		}
+	}
+
+	differences
+}
+
+/// Convert a signed commitment into the layout expected by bridge contracts and back.
+#[derive(StructOpt)]
+#[structopt(about = "Convert a SCALE-encoded signed commitment into contract calldata bytes")]
+pub struct CommitmentContractBytes {
+	/// SCALE-encoded signed commitment, or contract bytes with `--decode`.
+	pub input: Bytes,
+	/// Decode contract bytes back into a signed commitment.
+	#[structopt(long)]
+	pub decode: bool,
+}
+
+impl CommitmentContractBytes {
+	pub fn run(self) -> anyhow::Result<()> {
+		if self.decode {
+			let signed = from_contract_bytes(&self.input.0)?;
+			println!("Signed commitment: {:?}", signed);
+			println!("SCALE-encoded:\n\t 0x{}", hex::encode(signed.encode()));
+		} else {
+			let signed = SignedCommitment::decode(&mut &*self.input.0)?;
+			println!("0x{}", hex::encode(to_contract_bytes(&signed)));
+		}
+		Ok(())
+	}
+}
+
+/// Length of a raw ECDSA signature.
+const SIGNATURE_LEN: usize = 65;
+
+/// Serialize a signed commitment to the layout expected by bridge contracts.
+///
+/// The layout is:
+/// - SCALE-encoded commitment,
+/// - number of signature slots (validators) as big-endian `u32`,
+/// - bitfield of present signatures, `ceil(slots / 8)` bytes, slot `i` being bit `i % 8`
+///   (least significant first) of byte `i / 8`,
+/// - raw 65-byte signatures of present slots, in slot order.
+pub fn to_contract_bytes(signed: &SignedCommitment) -> Vec<u8> {
+	let mut bytes = signed.commitment.encode();
+	bytes.extend((signed.signatures.len() as u32).to_be_bytes());
+
+	let mut bitfield = vec![0u8; signed.signatures.len().div_ceil(8)];
+	for (index, signature) in signed.signatures.iter().enumerate() {
+		if signature.is_some() {
+			bitfield[index / 8] |= 1 << (index % 8);
+		}
+	}
+	bytes.extend(bitfield);
+
+	for signature in signed.signatures.iter().flatten() {
+		let raw: &[u8] = signature.as_ref();
+		bytes.extend(raw);
+	}
+
+	bytes
+}
+
+/// Deserialize a signed commitment from the layout produced by [`to_contract_bytes`].
+pub fn from_contract_bytes(mut bytes: &[u8]) -> anyhow::Result<SignedCommitment> {
+	let commitment = Commitment::<u32>::decode(&mut bytes)?;
+
+	let mut slots = [0u8; 4];
+	slots.copy_from_slice(take(&mut bytes, 4)?);
+	let slots = u32::from_be_bytes(slots) as usize;
+	let bitfield = take(&mut bytes, slots.div_ceil(8))?.to_vec();
+
+	let mut signatures = Vec::with_capacity(slots);
+	for index in 0..slots {
+		if bitfield[index / 8] & (1 << (index % 8)) == 0 {
+			signatures.push(None);
+			continue;
+		}
+		let mut raw = [0u8; SIGNATURE_LEN];
+		raw.copy_from_slice(take(&mut bytes, SIGNATURE_LEN)?);
+		signatures.push(Some(ecdsa::Signature::from_raw(raw).into()));
+	}
+
+	let present = bitfield.iter().map(|byte| byte.count_ones() as usize).sum::<usize>();
+	if present != signatures.iter().flatten().count() {
+		anyhow::bail!("Bitfield has bits set beyond {} signature slots", slots);
+	}
+	if !bytes.is_empty() {
+		anyhow::bail!("Unexpected {} trailing bytes", bytes.len());
+	}
+
+	Ok(SignedCommitment { commitment, signatures })
+}
+
+/// Split off first `len` bytes of the input.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+	if bytes.len() < len {
+		anyhow::bail!("Unexpected end of input: {} vs {} bytes", bytes.len(), len);
+	}
+	let (head, tail) = bytes.split_at(len);
+	*bytes = tail;
+	Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+
+	fn signature(byte: u8) -> Option<Signature> {
+		Some(ecdsa::Signature::from_raw([byte; 65]).into())
+	}
+
+	#[test]
+	fn signing_payload_should_be_keccak_of_encoded_commitment() {
+		// given
+		let root = H256::repeat_byte(0xab);
+		let commitment = mmr_root_commitment(5, 7, root);
+
+		// when
+		let (encoded, prehash) = signing_payload(&commitment);
+
+		// then
+		assert_eq!(
+			encoded,
+			hex!(
+				"04 6d68 80 abababababababababababababababababababababababababababababababab
+				05000000 0700000000000000"
+			)
+			.to_vec()
+		);
+		assert_eq!(prehash, keccak_256(&commitment.encode()));
+	}
+
+	#[test]
+	fn signed_commitment_should_verify_against_signing_prehash() {
+		// given
+		let pair = ecdsa::Pair::from_string("//Alice", None).unwrap();
+		let commitment = mmr_root_commitment(5, 7, H256::repeat_byte(0xab));
+
+		// when
+		let signature = sign_commitment(&pair, &commitment);
+
+		// then
+		let (_, prehash) = signing_payload(&commitment);
+		assert!(ecdsa::Pair::verify_prehashed(&signature, &prehash, &pair.public()));
+	}
+
+	#[test]
+	fn should_diff_commitments_differing_in_one_signature() {
+		// given
+		let a = SignedCommitment {
+			commitment: mmr_root_commitment(5, 7, H256::repeat_byte(0xab)),
+			signatures: vec![signature(1), signature(2), None],
+		};
+		let mut b = SignedCommitment::decode(&mut &*a.encode()).unwrap();
+		b.signatures[1] = signature(3);
+
+		// when
+		let differences = diff_commitments(&a, &b);
+
+		// then
+		assert_eq!(differences, vec![Difference::Signature(1, signature(2), signature(3))]);
+		assert!(diff_commitments(&a, &a).is_empty());
+	}
+
+	#[test]
+	fn contract_bytes_should_round_trip() {
+		// given
+		let signed = SignedCommitment {
+			commitment: mmr_root_commitment(5, 7, H256::repeat_byte(0xab)),
+			signatures: vec![
+				signature(1),
+				None,
+				signature(2),
+				None,
+				None,
+				None,
+				None,
+				None,
+				signature(3),
+			],
+		};
+
+		// when
+		let bytes = to_contract_bytes(&signed);
+
+		// then
+		let commitment_len = signed.commitment.encode().len();
+		let bitfield = &bytes[commitment_len + 4..commitment_len + 6];
+		assert_eq!(bitfield, &[0b0000_0101, 0b0000_0001]);
+		assert_eq!(
+			bitfield.iter().map(|byte| byte.count_ones() as usize).sum::<usize>(),
+			signed.no_of_signatures()
+		);
+		assert_eq!(bytes.len(), commitment_len + 4 + 2 + 3 * 65);
+		assert_eq!(from_contract_bytes(&bytes).unwrap(), signed);
+	}
+
+	#[test]
+	fn should_reject_truncated_contract_bytes() {
+		let signed = SignedCommitment {
+			commitment: mmr_root_commitment(5, 7, H256::repeat_byte(0xab)),
+			signatures: vec![signature(1)],
+		};
+		let bytes = to_contract_bytes(&signed);
+
+		assert!(from_contract_bytes(&bytes[..bytes.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn should_reject_bitfield_bits_beyond_slots() {
+		// given
+		let signed = SignedCommitment {
+			commitment: mmr_root_commitment(5, 7, H256::repeat_byte(0xab)),
+			signatures: vec![signature(1)],
+		};
+		let mut bytes = to_contract_bytes(&signed);
+		let commitment_len = signed.commitment.encode().len();
+
+		// when
+		bytes[commitment_len + 4] |= 0b0000_1000;
+
+		// then
+		assert!(from_contract_bytes(&bytes).is_err());
+	}
+}
@@ -18,7 +18,7 @@ use crate::cli::{
 	uncompress_authorities::{uncompress_beefy_ids, uncompressed_to_eth},
 	utils::{Authorities, Bytes},
 };
-use beefy_merkle_tree::Keccak256;
+use beefy_merkle_tree::{Hasher, Keccak256};
 use parity_scale_codec::{Decode, Encode};
 use sp_core::H256;
 use structopt::StructOpt;
@@ -34,6 +34,11 @@ pub enum BeefyMerkleTree {
 		leaf_index: usize,
 		/// A SCALE-encoded vector of BEEFY authority ids (compressed public key).
 		authorities: Authorities,
+		#[structopt(flatten)]
+		hashing: TreeHashing,
+		/// Output format of the proof (`scale` or `solidity`).
+		#[structopt(long, default_value = "scale")]
+		format: ProofFormat,
 	},
 	/// Verify a merkle proof given root hash and the proof content.
 	VerifyProof {
@@ -47,6 +52,8 @@ pub enum BeefyMerkleTree {
 		leaf_index: usize,
 		/// SCALE-encoded value of the leaf node (it's not part of the proof).
 		leaf_value: Bytes,
+		#[structopt(flatten)]
+		hashing: TreeHashing,
 	},
 }
 
@@ -56,10 +63,15 @@ impl BeefyMerkleTree {
 			Self::GenerateProof {
 				authorities,
 				leaf_index,
+				hashing,
+				format,
 			} => {
 				let uncompressed = uncompress_beefy_ids(authorities.0)?;
-				let eth_addresses = uncompressed_to_eth(uncompressed);
-				print_generated_merkle_proof(eth_addresses, leaf_index)
+				let eth_addresses = uncompressed_to_eth(uncompressed).collect::<Vec<_>>();
+				for (index, address) in eth_addresses.iter().enumerate() {
+					println!("[{}] Ethereum address: {}", index, address);
+				}
+				print_generated_merkle_proof(eth_addresses.into_iter(), leaf_index, hashing.algorithm()?, format)
 			}
 			Self::VerifyProof {
 				root,
@@ -67,7 +79,15 @@ impl BeefyMerkleTree {
 				number_of_leaves,
 				leaf_index,
 				leaf_value,
-			} => verify_merkle_proof(root, proof.0, number_of_leaves, leaf_index, leaf_value.0),
+				hashing,
+			} => verify_merkle_proof(
+				root,
+				proof.0,
+				number_of_leaves,
+				leaf_index,
+				leaf_value.0,
+				hashing.algorithm()?,
+			),
 		}
 	}
 }
@@ -83,6 +103,11 @@ pub enum ParaMerkleTree {
 		leaf_index: usize,
 		/// A list of raw `HeadData`.
 		heads: Vec<Bytes>, // TODO [ToDr] Add ParaId
+		#[structopt(flatten)]
+		hashing: TreeHashing,
+		/// Output format of the proof (`scale` or `solidity`).
+		#[structopt(long, default_value = "scale")]
+		format: ProofFormat,
 	},
 	/// Verify a merkle proof given root hash and the proof content.
 	VerifyProof {
@@ -96,15 +121,22 @@ pub enum ParaMerkleTree {
 		leaf_index: usize,
 		/// SCALE-encoded value of the leaf node (it's not part of the proof).
 		leaf_value: Bytes,
+		#[structopt(flatten)]
+		hashing: TreeHashing,
 	},
 }
 
 impl ParaMerkleTree {
 	pub fn run(self) -> anyhow::Result<()> {
 		match self {
-			Self::GenerateProof { heads, leaf_index } => {
+			Self::GenerateProof {
+				heads,
+				leaf_index,
+				hashing,
+				format,
+			} => {
 				let raw_heads = heads.into_iter().map(|x| x.0);
-				print_generated_merkle_proof(raw_heads, leaf_index)
+				print_generated_merkle_proof(raw_heads, leaf_index, hashing.algorithm()?, format)
 			}
 			Self::VerifyProof {
 				root,
@@ -112,17 +144,158 @@ impl ParaMerkleTree {
 				number_of_leaves,
 				leaf_index,
 				leaf_value,
-			} => verify_merkle_proof(root, proof.0, number_of_leaves, leaf_index, leaf_value.0),
+				hashing,
+			} => verify_merkle_proof(
+				root,
+				proof.0,
+				number_of_leaves,
+				leaf_index,
+				leaf_value.0,
+				hashing.algorithm()?,
+			),
 		}
 	}
 }
 
+/// Estimate merkle proof size for capacity planning.
+#[derive(StructOpt)]
+#[structopt(about = "Print the maximal size of a merkle proof for given number of leaves.")]
+pub struct ProofSize {
+	/// Number of leaves in the tree.
+	#[structopt(long)]
+	pub leaves: usize,
+}
+
+impl ProofSize {
+	pub fn run(self) -> anyhow::Result<()> {
+		println!("Tree depth: {}", tree_depth(self.leaves));
+		println!("Max proof size: {} bytes", proof_size_bytes(self.leaves));
+		Ok(())
+	}
+}
+
+/// Number of levels above the leaves in a tree with given number of leaves.
+fn tree_depth(number_of_leaves: usize) -> usize {
+	let mut depth = 0;
+	let mut width = number_of_leaves;
+	while width > 1 {
+		width = width.div_ceil(2);
+		depth += 1;
+	}
+	depth
+}
+
+/// Maximal size (in bytes) of the proof nodes for a tree with given number of leaves.
+///
+/// Only the 32-byte nodes are counted; the SCALE encoding of a proof is prefixed with its
+/// compact-encoded length on top of that. Proofs for leaves promoted in odd-sized trees may
+/// be shorter.
+pub fn proof_size_bytes(number_of_leaves: usize) -> usize {
+	tree_depth(number_of_leaves) * 32
+}
+
+/// Output format of a generated merkle proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofFormat {
+	/// SCALE-encoded proof, as expected by Substrate tooling.
+	Scale,
+	/// `bytes32[]` literal, as expected by Solidity `abi.encode`.
+	Solidity,
+}
+
+impl std::str::FromStr for ProofFormat {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> anyhow::Result<Self> {
+		match s {
+			"scale" => Ok(Self::Scale),
+			"solidity" => Ok(Self::Solidity),
+			_ => Err(anyhow::format_err!(
+				"Unknown proof format: {} (expected scale or solidity)",
+				s
+			)),
+		}
+	}
+}
+
+/// Hash algorithm used to build the merkle tree, selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+	/// Keccak-256, as used by BEEFY and Ethereum.
+	Keccak,
+	/// Blake2b-256, as commonly used in Substrate.
+	Blake2,
+}
+
+impl std::str::FromStr for HashAlgorithm {
+	type Err = anyhow::Error;
+
+	fn from_str(s: &str) -> anyhow::Result<Self> {
+		match s {
+			"keccak" => Ok(Self::Keccak),
+			"blake2" => Ok(Self::Blake2),
+			_ => Err(anyhow::format_err!(
+				"Unknown hash algorithm: {} (expected keccak or blake2)",
+				s
+			)),
+		}
+	}
+}
+
+impl std::fmt::Display for HashAlgorithm {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Keccak => write!(f, "keccak"),
+			Self::Blake2 => write!(f, "blake2"),
+		}
+	}
+}
+
+/// Hash algorithms used for leaves and inner nodes of the merkle tree.
+#[derive(StructOpt)]
+pub struct TreeHashing {
+	/// Hash algorithm used for leaves (`keccak` or `blake2`). Also accepted as `--hash`.
+	#[structopt(long, alias = "hash", default_value = "keccak")]
+	leaf_hash: HashAlgorithm,
+	/// Hash algorithm used to combine inner nodes, defaults to the leaf hash. Must equal
+	/// `--leaf-hash` until `beefy-merkle-tree` supports separate node hashing.
+	#[structopt(long)]
+	node_hash: Option<HashAlgorithm>,
+}
+
+impl TreeHashing {
+	/// Resolve the hash algorithm to build the tree with.
+	///
+	/// `beefy-merkle-tree` uses the same hasher for leaves and nodes, so combinations of
+	/// different algorithms are rejected.
+	fn algorithm(&self) -> anyhow::Result<HashAlgorithm> {
+		match self.node_hash {
+			Some(node_hash) if node_hash != self.leaf_hash => Err(anyhow::format_err!(
+				"Unsupported --leaf-hash {} with --node-hash {}: leaves and nodes must use the same hash",
+				self.leaf_hash,
+				node_hash
+			)),
+			_ => Ok(self.leaf_hash),
+		}
+	}
+}
+
+/// Blake2-256 hasher for the merkle tree.
+pub struct Blake2Hasher;
+
+impl Hasher for Blake2Hasher {
+	fn hash(data: &[u8]) -> [u8; 32] {
+		sp_core::hashing::blake2_256(data)
+	}
+}
+
 type Proof = Vec<H256>;
 type Leaf = Vec<u8>;
 
 fn generate_merkle_proof<T: AsRef<[u8]>>(
 	items: impl Iterator<Item = T>,
 	leaf_index: usize,
+	hash: HashAlgorithm,
 ) -> anyhow::Result<(H256, Proof, Leaf, usize)> {
 	let items = items.collect::<Vec<_>>();
 	let number_of_leaves = items.len();
@@ -131,8 +304,10 @@ fn generate_merkle_proof<T: AsRef<[u8]>>(
 		.map(|x| x.as_ref().to_vec())
 		.ok_or_else(|| anyhow::format_err!("Leaf index out of bounds: {} vs {}", leaf_index, items.len(),))?;
 
-	let beefy_merkle_tree::MerkleProof { root, proof, .. } =
-		beefy_merkle_tree::merkle_proof::<Keccak256, _, _>(items, leaf_index);
+	let beefy_merkle_tree::MerkleProof { root, proof, .. } = match hash {
+		HashAlgorithm::Keccak => beefy_merkle_tree::merkle_proof::<Keccak256, _, _>(items, leaf_index),
+		HashAlgorithm::Blake2 => beefy_merkle_tree::merkle_proof::<Blake2Hasher, _, _>(items, leaf_index),
+	};
 	let proof = proof.into_iter().map(Into::into).collect();
 
 	Ok((root.into(), proof, leaf, number_of_leaves))
@@ -141,32 +316,47 @@ fn generate_merkle_proof<T: AsRef<[u8]>>(
 fn print_generated_merkle_proof<T: AsRef<[u8]>>(
 	items: impl Iterator<Item = T>,
 	leaf_index: usize,
+	hash: HashAlgorithm,
+	format: ProofFormat,
 ) -> anyhow::Result<()> {
-	let (root, proof, leaf, number_of_leaves) = generate_merkle_proof(items, leaf_index)?;
+	let (root, proof, leaf, number_of_leaves) = generate_merkle_proof(items, leaf_index, hash)?;
 	println!();
 	println!("Root: {:?}", root);
 	println!("Leaf index: {}", leaf_index);
 	println!("Number of leaves: {}", number_of_leaves);
-	println!("SCALE-encoded proof: 0x{}", hex::encode(proof.encode()));
-	println!("SCALE-encoded leaf value: 0x{}", hex::encode(&leaf));
+	match format {
+		ProofFormat::Scale => {
+			println!("SCALE-encoded proof: 0x{}", hex::encode(proof.encode()));
+			println!("SCALE-encoded leaf value: 0x{}", hex::encode(&leaf));
+		}
+		ProofFormat::Solidity => {
+			println!("Proof (bytes32[]): {}", solidity_proof(&proof));
+			println!("Leaf value (bytes): 0x{}", hex::encode(&leaf));
+		}
+	}
 	println!();
 
 	Ok(())
 }
 
+/// Format the proof as a Solidity `bytes32[]` literal.
+fn solidity_proof(proof: &Proof) -> String {
+	let words = proof
+		.iter()
+		.map(|node| format!("0x{}", hex::encode(node)))
+		.collect::<Vec<_>>();
+	format!("[{}]", words.join(", "))
+}
+
 fn verify_merkle_proof(
 	root: H256,
 	proof: Vec<u8>,
 	number_of_leaves: usize,
 	leaf_index: usize,
 	leaf_value: Vec<u8>,
+	hash: HashAlgorithm,
 ) -> anyhow::Result<()> {
-	let proof: Proof = Decode::decode(&mut &*proof)?;
-	let convert = |c: H256| c.to_fixed_bytes();
-	let root = convert(root);
-	let proof = proof.into_iter().map(convert).collect::<Vec<_>>();
-
-	if beefy_merkle_tree::verify_proof::<Keccak256, _, _>(&root, proof, number_of_leaves, leaf_index, &leaf_value) {
+	if check_merkle_proof(root, proof, number_of_leaves, leaf_index, leaf_value, hash)? {
 		println!("\n✅ Proof is correct.\n");
 	} else {
 		println!("\n❌ Proof is INCORRECT.\n");
@@ -175,6 +365,35 @@ fn verify_merkle_proof(
 	Ok(())
 }
 
+fn check_merkle_proof(
+	root: H256,
+	proof: Vec<u8>,
+	number_of_leaves: usize,
+	leaf_index: usize,
+	leaf_value: Vec<u8>,
+	hash: HashAlgorithm,
+) -> anyhow::Result<bool> {
+	let proof: Proof = Decode::decode(&mut &*proof)?;
+	let convert = |c: H256| c.to_fixed_bytes();
+	let root = convert(root);
+	let proof = proof.into_iter().map(convert).collect::<Vec<_>>();
+
+	let valid = match hash {
+		HashAlgorithm::Keccak => {
+			beefy_merkle_tree::verify_proof::<Keccak256, _, _>(&root, proof, number_of_leaves, leaf_index, &leaf_value)
+		}
+		HashAlgorithm::Blake2 => beefy_merkle_tree::verify_proof::<Blake2Hasher, _, _>(
+			&root,
+			proof,
+			number_of_leaves,
+			leaf_index,
+			&leaf_value,
+		),
+	};
+
+	Ok(valid)
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -195,9 +414,106 @@ mod tests {
 		let leaf_index = 0;
 
 		// when
-		let (root, proof, leaf, _) = generate_merkle_proof(items, leaf_index).unwrap();
+		let (root, proof, leaf, _) = generate_merkle_proof(items, leaf_index, HashAlgorithm::Keccak).unwrap();
+
+		// then
+		verify_merkle_proof(root, proof.encode(), len, leaf_index, leaf, HashAlgorithm::Keccak).unwrap();
+	}
+
+	#[test]
+	fn should_generate_and_verify_proofs_with_selected_hash() {
+		// given
+		let items = || vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()].into_iter();
+		let leaf_index = 1;
+
+		// when
+		let (keccak_root, keccak_proof, leaf, len) =
+			generate_merkle_proof(items(), leaf_index, HashAlgorithm::Keccak).unwrap();
+		let (blake2_root, blake2_proof, _, _) =
+			generate_merkle_proof(items(), leaf_index, HashAlgorithm::Blake2).unwrap();
 
 		// then
-		verify_merkle_proof(root, proof.encode(), len, leaf_index, leaf).unwrap();
+		assert_ne!(keccak_root, blake2_root);
+		let check = |root, proof: &Proof, hash| {
+			check_merkle_proof(root, proof.encode(), len, leaf_index, leaf.clone(), hash).unwrap()
+		};
+		assert!(check(keccak_root, &keccak_proof, HashAlgorithm::Keccak));
+		assert!(check(blake2_root, &blake2_proof, HashAlgorithm::Blake2));
+		assert!(!check(keccak_root, &keccak_proof, HashAlgorithm::Blake2));
+		assert!(!check(blake2_root, &blake2_proof, HashAlgorithm::Keccak));
+	}
+
+	#[test]
+	fn proof_size_should_match_largest_generated_proof() {
+		for number_of_leaves in (1..=10).chain([16, 17]) {
+			// given
+			let items = (0..number_of_leaves).map(|i| vec![i as u8]).collect::<Vec<_>>();
+
+			// when
+			let sizes = (0..number_of_leaves)
+				.map(|leaf_index| {
+					let (_, proof, _, _) =
+						generate_merkle_proof(items.iter(), leaf_index, HashAlgorithm::Keccak).unwrap();
+					proof.encode().len() - parity_scale_codec::Compact(proof.len() as u32).encode().len()
+				})
+				.collect::<Vec<_>>();
+
+			// then
+			assert_eq!(sizes.iter().max(), Some(&proof_size_bytes(number_of_leaves)));
+		}
+		assert_eq!(proof_size_bytes(0), 0);
+	}
+
+	#[test]
+	fn keccak_leaf_and_node_hashing_should_produce_expected_root() {
+		// given
+		let authorities = Authorities(vec![
+			hex!("039346ec0021405ec103c2baac8feff9d6fb75851318fb03781edf29f05f2ffeb7").unchecked_into(),
+			hex!("03fe6b333420b90689158643ccad94e62d707de1a80726d53aa04657fec14afd3e").unchecked_into(),
+		]);
+		let items = uncompressed_to_eth(uncompress_beefy_ids(authorities.0).unwrap());
+		let hashing =
+			TreeHashing::from_iter_safe(&["hashing", "--leaf-hash", "keccak", "--node-hash", "keccak"]).unwrap();
+
+		// when
+		let (root, _, _, _) = generate_merkle_proof(items, 0, hashing.algorithm().unwrap()).unwrap();
+
+		// then
+		assert_eq!(
+			root,
+			H256::from(hex!("19fb62eeb283aedf05950efa7607ed5a6d27533be44400d7b63ec80c812f2789"))
+		);
+	}
+
+	#[test]
+	fn should_reject_mixed_leaf_and_node_hashing() {
+		let parse = |args: &[&str]| TreeHashing::from_iter_safe(args).unwrap().algorithm();
+
+		assert_eq!(parse(&["hashing"]).unwrap(), HashAlgorithm::Keccak);
+		assert_eq!(parse(&["hashing", "--hash", "blake2"]).unwrap(), HashAlgorithm::Blake2);
+		assert!(parse(&["hashing", "--leaf-hash", "keccak", "--node-hash", "blake2"]).is_err());
+	}
+
+	#[test]
+	fn solidity_proof_should_contain_all_proof_nodes() {
+		// given
+		let items = (0..10u8).map(|i| vec![i]);
+		let (_, proof, _, _) = generate_merkle_proof(items, 3, HashAlgorithm::Keccak).unwrap();
+
+		// when
+		let formatted = solidity_proof(&proof);
+
+		// then
+		let words = formatted
+			.trim_start_matches('[')
+			.trim_end_matches(']')
+			.split(", ")
+			.collect::<Vec<_>>();
+		assert_eq!(words.len(), proof.len());
+		for (word, node) in words.iter().zip(&proof) {
+			assert!(word.starts_with("0x"));
+			assert_eq!(word.len(), 2 + 64);
+			assert_eq!(*word, format!("0x{}", hex::encode(node)));
+		}
 	}
 }
@@ -14,8 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::cli::utils::Bytes;
-use beefy_primitives::mmr::{MmrLeaf, MmrLeafVersion};
+use crate::cli::{
+	uncompress_authorities::{uncompress_beefy_ids, uncompressed_to_eth},
+	utils::{Authorities, Bytes},
+};
+use beefy_merkle_tree::Keccak256;
+use beefy_primitives::{
+	crypto::AuthorityId,
+	mmr::{MmrLeaf, MmrLeafVersion},
+};
 use parity_scale_codec::{Decode, Encode};
 use sp_core::H256;
 use structopt::StructOpt;
@@ -25,6 +32,9 @@ fn polkadot_leaf_version() -> MmrLeafVersion {
 	MmrLeafVersion::new(0, 0)
 }
 
+/// MMR Leaf as found in Polkadot-compatible runtimes.
+type Leaf = MmrLeaf<u32, H256, H256>;
+
 /// MMR related commands
 #[derive(StructOpt)]
 #[structopt(about = "Merkle Mountain Range related commands.")]
@@ -36,6 +46,10 @@ pub enum Mmr {
 		/// Leaf can be obtained via `mmr_generateProof` custom RPC method.
 		/// Since the RPC returns a SCALE-encoding of `Vec<u8>`, this method expects the same.
 		leaf: Bytes,
+		/// A SCALE-encoded vector of BEEFY authority ids (compressed public keys) to verify
+		/// against the next authority set root committed in the leaf.
+		#[structopt(long)]
+		next_authorities: Option<Authorities>,
 	},
 	/// Construct MMR Offchain storage key.
 	StorageKey {
@@ -49,32 +63,24 @@ pub enum Mmr {
 impl Mmr {
 	pub fn run(self) -> anyhow::Result<()> {
 		match self {
-			Self::DecodeLeaf { leaf } => {
-				// We support both `MmrLeaf` directly or a `DataOrHash::Data(MmrLeaf)` variant.
-				// Since `00` cannot be a beginning of SCALE-encoded Vec, we do a dummy detection
-				// below.
-				let mut leaf_content = if leaf.0.get(0) == Some(&0) {
-					&leaf.0[1..]
-				} else {
-					&*leaf.0
-				};
-				let leaf: Vec<u8> = Decode::decode(&mut leaf_content)?;
-				let leaf: MmrLeaf<u32, H256, H256> = Decode::decode(&mut &*leaf)?;
-				let (decoded_major, decoded_minor) = leaf.version.split();
-				let (known_major, known_minor) = polkadot_leaf_version().split();
-				if decoded_major != known_major {
-					return Err(anyhow::format_err!(
-						"Incompatible decoded leaf major: {} vs {}",
-						decoded_major,
-						known_major
-					));
-				} else if decoded_minor != known_minor {
-					println!(
-						"Warning: decoded leaf version minor {} != expected leaf version minor {}.",
-						decoded_minor, known_minor
-					);
-				}
+			Self::DecodeLeaf { leaf, next_authorities } => {
+				let (encoding, leaf) = decode_leaf(&leaf.0)?;
+				println!("Decoded as {}:", encoding);
 				println!("{:?}", leaf);
+
+				let next_set = &leaf.beefy_next_authority_set;
+				println!();
+				println!("Next authority set id: {}", next_set.id);
+				println!("Next authority set length: {}", next_set.len);
+				println!("Next authority set root: {:?}", next_set.root);
+
+				if let Some(authorities) = next_authorities {
+					if verify_next_authority_set(&leaf, authorities.0)? {
+						println!("\n✅ Next authority set matches the leaf.\n");
+					} else {
+						println!("\n❌ Next authority set does NOT match the leaf.\n");
+					}
+				}
 			}
 			Self::StorageKey { prefix, pos } => {
 				let key = (prefix.as_bytes(), pos).encode();
@@ -84,3 +90,162 @@ impl Mmr {
 		Ok(())
 	}
 }
+
+/// Wrapping of the double SCALE-encoded leaf given to `DecodeLeaf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LeafEncoding {
+	/// `DataOrHash::Data` variant, i.e. `00` followed by the encoded `Vec<u8>`.
+	DataOrHash,
+	/// Plain SCALE-encoded `Vec<u8>`.
+	Raw,
+}
+
+impl std::fmt::Display for LeafEncoding {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::DataOrHash => write!(f, "DataOrHash::Data(Vec<u8>)"),
+			Self::Raw => write!(f, "Vec<u8>"),
+		}
+	}
+}
+
+/// Decode a SCALE-encoded `Vec<u8>` containing an `MmrLeaf`, consuming the whole input.
+fn decode_wrapped_leaf(mut input: &[u8]) -> Option<Leaf> {
+	let leaf: Vec<u8> = Decode::decode(&mut input).ok()?;
+	if !input.is_empty() {
+		return None;
+	}
+
+	let mut leaf = &*leaf;
+	let decoded = Leaf::decode(&mut leaf).ok()?;
+	if !leaf.is_empty() {
+		return None;
+	}
+
+	Some(decoded)
+}
+
+/// Decode a double SCALE-encoded MMR Leaf and check its version.
+///
+/// We support both `MmrLeaf` directly or a `DataOrHash::Data(MmrLeaf)` variant. Each
+/// interpretation has to consume the input entirely, so a `00` prefix is only treated as
+/// the `DataOrHash` variant index if the rest decodes as a leaf. `DataOrHash` is tried first.
+fn decode_leaf(leaf: &[u8]) -> anyhow::Result<(LeafEncoding, Leaf)> {
+	let (encoding, leaf) = leaf
+		.strip_prefix(&[0])
+		.and_then(decode_wrapped_leaf)
+		.map(|leaf| (LeafEncoding::DataOrHash, leaf))
+		.or_else(|| decode_wrapped_leaf(leaf).map(|leaf| (LeafEncoding::Raw, leaf)))
+		.ok_or_else(|| anyhow::format_err!("Unable to decode leaf either as `DataOrHash::Data` or as `Vec<u8>`"))?;
+
+	let (decoded_major, decoded_minor) = leaf.version.split();
+	let (known_major, known_minor) = polkadot_leaf_version().split();
+	if decoded_major != known_major {
+		return Err(anyhow::format_err!(
+			"Incompatible decoded leaf major: {} vs {}",
+			decoded_major,
+			known_major
+		));
+	} else if decoded_minor != known_minor {
+		println!(
+			"Warning: decoded leaf version minor {} != expected leaf version minor {}.",
+			decoded_minor, known_minor
+		);
+	}
+	Ok((encoding, leaf))
+}
+
+/// Check that given BEEFY authority ids are the next authority set committed in the leaf.
+///
+/// The set root is a merkle root of Ethereum addresses of the authorities, so we rebuild it
+/// from the keys and compare it together with the set length.
+fn verify_next_authority_set(leaf: &Leaf, authorities: Vec<AuthorityId>) -> anyhow::Result<bool> {
+	let next_set = &leaf.beefy_next_authority_set;
+	if authorities.len() != next_set.len as usize {
+		return Ok(false);
+	}
+
+	let eth_addresses = uncompressed_to_eth(uncompress_beefy_ids(authorities)?);
+	let root: H256 = beefy_merkle_tree::merkle_root::<Keccak256, _, _>(eth_addresses).into();
+
+	Ok(root == next_set.root)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use beefy_primitives::mmr::BeefyNextAuthoritySet;
+	use hex_literal::hex;
+	use sp_core::crypto::UncheckedInto;
+
+	fn authorities() -> Vec<AuthorityId> {
+		vec![
+			hex!("039346ec0021405ec103c2baac8feff9d6fb75851318fb03781edf29f05f2ffeb7").unchecked_into(),
+			hex!("03fe6b333420b90689158643ccad94e62d707de1a80726d53aa04657fec14afd3e").unchecked_into(),
+		]
+	}
+
+	fn leaf_with_next_set(authorities: Vec<AuthorityId>) -> Leaf {
+		let len = authorities.len() as u32;
+		let eth_addresses = uncompressed_to_eth(uncompress_beefy_ids(authorities).unwrap());
+		let root = beefy_merkle_tree::merkle_root::<Keccak256, _, _>(eth_addresses).into();
+
+		MmrLeaf {
+			version: polkadot_leaf_version(),
+			parent_number_and_hash: (5, H256::repeat_byte(1)),
+			beefy_next_authority_set: BeefyNextAuthoritySet { id: 3, len, root },
+			parachain_heads: H256::repeat_byte(2),
+		}
+	}
+
+	#[test]
+	fn should_decode_leaf_and_verify_next_authority_set() {
+		// given
+		let leaf = leaf_with_next_set(authorities());
+		let encoded = leaf.encode().encode();
+
+		// when
+		let (encoding, decoded) = decode_leaf(&encoded).unwrap();
+
+		// then
+		assert_eq!(encoding, LeafEncoding::Raw);
+		assert_eq!(decoded, leaf);
+		assert_eq!(decoded.beefy_next_authority_set.id, 3);
+		assert!(verify_next_authority_set(&decoded, authorities()).unwrap());
+	}
+
+	#[test]
+	fn should_decode_data_or_hash_wrapped_leaf() {
+		// given
+		let leaf = leaf_with_next_set(authorities());
+		let mut encoded = vec![0];
+		encoded.extend(leaf.encode().encode());
+
+		// when
+		let (encoding, decoded) = decode_leaf(&encoded).unwrap();
+
+		// then
+		assert_eq!(encoding, LeafEncoding::DataOrHash);
+		assert_eq!(decoded, leaf);
+	}
+
+	#[test]
+	fn should_reject_empty_vec_leaf() {
+		// `00` is both an empty `Vec<u8>` and a `DataOrHash::Data` index with no content.
+		assert!(decode_leaf(&[0]).is_err());
+		assert!(decode_leaf(&[0, 0]).is_err());
+		assert!(decode_leaf(&[]).is_err());
+	}
+
+	#[test]
+	fn should_reject_mismatching_next_authority_set() {
+		// given
+		let leaf = leaf_with_next_set(authorities());
+		let mut other = authorities();
+		other.reverse();
+
+		// then
+		assert!(!verify_next_authority_set(&leaf, other).unwrap());
+		assert!(!verify_next_authority_set(&leaf, authorities()[..1].to_vec()).unwrap());
+	}
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+mod commitment;
 mod merkle_tree;
 mod mmr;
 mod uncompress_authorities;
@@ -26,9 +27,15 @@ use structopt::StructOpt;
 #[structopt(about = "BEEFY utilities")]
 pub enum Command {
 	UncompressBeefyId(uncompress_authorities::UncompressAuthorities),
+	CompressBeefyId(uncompress_authorities::CompressAuthority),
 	BeefyIdMerkleTree(merkle_tree::BeefyMerkleTree),
 	ParaHeadsMerkleTree(merkle_tree::ParaMerkleTree),
+	ProofSize(merkle_tree::ProofSize),
 	Mmr(mmr::Mmr),
+	CommitmentPayload(commitment::CommitmentPayload),
+	CommitmentDiff(commitment::CommitmentDiff),
+	SignCommitment(commitment::SignCommitment),
+	CommitmentContractBytes(commitment::CommitmentContractBytes),
 }
 
 impl Command {
@@ -36,9 +43,15 @@ impl Command {
 	pub fn run(self) -> anyhow::Result<()> {
 		match self {
 			Self::UncompressBeefyId(cmd) => cmd.run(),
+			Self::CompressBeefyId(cmd) => cmd.run(),
 			Self::BeefyIdMerkleTree(cmd) => cmd.run(),
 			Self::ParaHeadsMerkleTree(cmd) => cmd.run(),
+			Self::ProofSize(cmd) => cmd.run(),
 			Self::Mmr(cmd) => cmd.run(),
+			Self::CommitmentPayload(cmd) => cmd.run(),
+			Self::CommitmentDiff(cmd) => cmd.run(),
+			Self::SignCommitment(cmd) => cmd.run(),
+			Self::CommitmentContractBytes(cmd) => cmd.run(),
 		}
 	}
 }
@@ -14,9 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::cli::utils::{parse_hex, Authorities};
+use crate::cli::utils::{parse_hex, Authorities, Bytes};
 use beefy_primitives::crypto::AuthorityId;
-use parity_scale_codec::Decode;
+use parity_scale_codec::{Decode, Encode};
 use structopt::StructOpt;
 
 /// Decode and uncompress encoded BEEFY id(s).
@@ -56,24 +56,85 @@ impl UncompressAuthorities {
 	}
 }
 
+/// Compress an uncompressed secp256k1 public key into a BEEFY id.
+#[derive(StructOpt)]
+#[structopt(about = "Compress a secp256k1 public key into a SCALE-encoded BEEFY authority id")]
+pub struct CompressAuthority {
+	/// Uncompressed secp256k1 public key (65 bytes, `0x04` prefixed).
+	pub uncompressed: Bytes,
+}
+
+impl CompressAuthority {
+	pub fn run(self) -> anyhow::Result<()> {
+		let len = self.uncompressed.0.len();
+		let raw: [u8; 65] = self
+			.uncompressed
+			.0
+			.try_into()
+			.map_err(|_| anyhow::format_err!("Invalid uncompressed key length: {} vs 65", len))?;
+		let id = compress(&raw)?;
+		println!("[{:?}] Compressed:\n\t 0x{}", id, hex::encode(id.encode()));
+		Ok(())
+	}
+}
+
 /// Convert BEEFY authority ids into uncompressed secp256k1 PublicKeys
 pub fn uncompress_beefy_ids(ids: Vec<AuthorityId>) -> anyhow::Result<Vec<libsecp256k1::PublicKey>> {
 	let mut uncompressed = vec![];
 	for id in ids {
-		let public =
-			libsecp256k1::PublicKey::parse_slice(&*id.as_ref(), Some(libsecp256k1::PublicKeyFormat::Compressed))?;
+		let public = uncompress(&id)?;
 		println!("[{:?}] Uncompressed:\n\t {}", id, hex::encode(public.serialize()));
 		uncompressed.push(public);
 	}
 	Ok(uncompressed)
 }
 
+/// Uncompress a single BEEFY authority id into a secp256k1 public key.
+pub fn uncompress(id: &AuthorityId) -> anyhow::Result<libsecp256k1::PublicKey> {
+	let public = libsecp256k1::PublicKey::parse_slice(&*id.as_ref(), Some(libsecp256k1::PublicKeyFormat::Compressed))?;
+	Ok(public)
+}
+
+/// Compress a raw 65-byte, `0x04` prefixed secp256k1 public key into a BEEFY authority id.
+///
+/// Hybrid (`0x06`/`0x07` prefixed) keys are rejected.
+pub fn compress(uncompressed: &[u8; 65]) -> anyhow::Result<AuthorityId> {
+	if uncompressed[0] != 0x04 {
+		anyhow::bail!("Expected 0x04 prefixed uncompressed key, got 0x{:02x}", uncompressed[0]);
+	}
+	let public = libsecp256k1::PublicKey::parse(uncompressed)?;
+	Ok(sp_core::ecdsa::Public::from_raw(public.serialize_compressed()).into())
+}
+
 /// Convert uncompressed secp256k1 Public Keys to Ethereum Addresses.
-pub fn uncompressed_to_eth(uncompressed: Vec<libsecp256k1::PublicKey>) -> impl Iterator<Item = Vec<u8>> {
-	uncompressed
-		.into_iter()
-		.map(|k| k.serialize())
-		.map(|uncompressed_raw| beefy_merkle_tree::Keccak256::hash(&uncompressed_raw[1..])[12..].to_vec())
+pub fn uncompressed_to_eth(uncompressed: Vec<libsecp256k1::PublicKey>) -> impl Iterator<Item = EthAddress> {
+	uncompressed.into_iter().map(EthAddress::from)
+}
+
+/// An Ethereum address, i.e. last 20 bytes of keccak256 hash of uncompressed public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthAddress(pub [u8; 20]);
+
+impl From<libsecp256k1::PublicKey> for EthAddress {
+	fn from(public: libsecp256k1::PublicKey) -> Self {
+		let uncompressed_raw = public.serialize();
+		let hash = beefy_merkle_tree::Keccak256::hash(&uncompressed_raw[1..]);
+		let mut address = [0u8; 20];
+		address.copy_from_slice(&hash[12..]);
+		Self(address)
+	}
+}
+
+impl AsRef<[u8]> for EthAddress {
+	fn as_ref(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl std::fmt::Display for EthAddress {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "0x{}", hex::encode(self.0))
+	}
 }
 
 fn beefy_id_from_hex(id: &str) -> anyhow::Result<AuthorityId> {
@@ -81,3 +142,57 @@ fn beefy_id_from_hex(id: &str) -> anyhow::Result<AuthorityId> {
 	let auth_id = AuthorityId::decode(&mut &*encoded)?;
 	Ok(auth_id)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use hex_literal::hex;
+	use sp_core::crypto::UncheckedInto;
+
+	#[test]
+	fn should_round_trip_compressed_and_uncompressed_ids() {
+		// given
+		let id: AuthorityId =
+			hex!("039346ec0021405ec103c2baac8feff9d6fb75851318fb03781edf29f05f2ffeb7").unchecked_into();
+
+		// when
+		let uncompressed = uncompress(&id).unwrap().serialize();
+
+		// then
+		let compressed: &[u8] = id.as_ref();
+		assert_eq!(uncompressed[0], 0x04);
+		assert_eq!(&uncompressed[1..33], &compressed[1..]);
+		assert_eq!(compress(&uncompressed).unwrap(), id);
+	}
+
+	#[test]
+	fn should_convert_beefy_id_to_eth_address() {
+		// given
+		let alice: AuthorityId =
+			hex!("020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1").unchecked_into();
+
+		// when
+		let address = uncompressed_to_eth(uncompress_beefy_ids(vec![alice]).unwrap())
+			.next()
+			.unwrap();
+
+		// then
+		assert_eq!(address, EthAddress(hex!("e04cc55ebee1cbce552f250e85c57b70b2e2625b")));
+		assert_eq!(address.to_string(), "0xe04cc55ebee1cbce552f250e85c57b70b2e2625b");
+	}
+
+	#[test]
+	fn should_reject_malformed_keys() {
+		let id: AuthorityId =
+			hex!("059346ec0021405ec103c2baac8feff9d6fb75851318fb03781edf29f05f2ffeb7").unchecked_into();
+
+		assert!(uncompress(&id).is_err());
+		assert!(compress(&[0u8; 65]).is_err());
+
+		let valid: AuthorityId =
+			hex!("039346ec0021405ec103c2baac8feff9d6fb75851318fb03781edf29f05f2ffeb7").unchecked_into();
+		let mut hybrid = uncompress(&valid).unwrap().serialize();
+		hybrid[0] = 0x06 | (hybrid[64] & 1);
+		assert!(compress(&hybrid).is_err());
+	}
+}